@@ -1,12 +1,18 @@
 use std::str::FromStr;
+use std::time::Duration;
 use std::{env, fmt};
 
-use chrono::{DateTime, FixedOffset, Local};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone};
+use futures::future::join_all;
 use quick_xml::Reader;
 use quick_xml::events::Event;
 use sqlite::Connection;
 use url::Url;
 
+mod migrations;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(PartialEq)]
 enum Tag {
     Item,
@@ -14,6 +20,7 @@ enum Tag {
     Description,
     PubDate,
     Link,
+    Guid,
     Other(String),
 } use Tag::*;
 
@@ -22,32 +29,77 @@ impl FromStr for Tag {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s {
-            "item" => Item,
+            "item" | "entry" => Item,
             "title" => Title,
-            "description" => Description,
-            "pubDate" => PubDate,
+            "description" | "summary" | "content" => Description,
+            "pubDate" | "updated" | "published" => PubDate,
             "link" => Link,
+            "guid" => Guid,
             other => Other(other.to_string()),
         })
     }
 }
 
 
+fn local_offset() -> FixedOffset {
+    *Local::now().offset()
+}
+
+/// Non-standard `pubDate`/`updated` formats seen in the wild, tried in
+/// order after RFC 2822 and RFC 3339 both fail to parse. Each is treated
+/// as already being in the local offset.
+const NAIVE_DATE_TIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"];
+const NAIVE_DATE_FORMATS: &[&str] = &["%Y-%m-%d"];
+
+fn parse_date(data: &str) -> Option<DateTime<FixedOffset>> {
+    let offset = local_offset();
+
+    if let Ok(date) = DateTime::parse_from_rfc2822(data) {
+        return Some(date.with_timezone(&offset));
+    }
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(data) {
+        return Some(date.with_timezone(&offset));
+    }
+
+    for format in NAIVE_DATE_TIME_FORMATS {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(data, format) {
+            return offset.from_local_datetime(&naive).single();
+        }
+    }
+
+    for format in NAIVE_DATE_FORMATS {
+        if let Ok(date) = chrono::NaiveDate::parse_from_str(data, format) {
+            let naive = date.and_hms_opt(0, 0, 0)?;
+            return offset.from_local_datetime(&naive).single();
+        }
+    }
+
+    None
+}
+
 #[derive(Clone, Debug)]
 struct Article {
     title: String,
     description: String,
     pub_date: Option<DateTime<FixedOffset>>,
+    pub_date_inferred: bool,
     link: Option<Url>,
+    guid: Option<String>,
 }
 
 impl Article {
-    fn new() -> Self {
+    /// `fetched_at` seeds `pub_date` as an inferred fallback so an article
+    /// whose feed omits (or mangles) its date still sorts and displays
+    /// sensibly instead of collapsing to "unavailable".
+    fn new(fetched_at: DateTime<FixedOffset>) -> Self {
         Article {
             title: String::new(),
             description: String::new(),
-            pub_date: None,
+            pub_date: Some(fetched_at),
+            pub_date_inferred: true,
             link: None,
+            guid: None,
         }
     }
 
@@ -55,15 +107,14 @@ impl Article {
         match tag {
             Title => self.title = data.to_string(),
             Description => self.description = data.to_string(),
-            PubDate => if let Ok(pub_date) = DateTime::parse_from_rfc2822(data) {
-                let now = Local::now();
-                let tz = now.offset();
-
-                self.pub_date = Some(pub_date.with_timezone(tz));
+            PubDate => if let Some(pub_date) = parse_date(data) {
+                self.pub_date = Some(pub_date);
+                self.pub_date_inferred = false;
             },
             Link => if let Ok(link) = Url::parse(data) {
                 self.link = Some(link);
             },
+            Guid => self.guid = Some(data.to_string()),
             _ => (),
         }
     }
@@ -71,19 +122,47 @@ impl Article {
 
 impl fmt::Display for Article {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let pub_date = self.pub_date.map_or(String::from("unavailable"), |d| d.to_string());
-        
+        let pub_date = self.pub_date.map_or(String::from("unavailable"), |d| {
+            if self.pub_date_inferred {
+                format!("{d} (inferred)")
+            } else {
+                d.to_string()
+            }
+        });
+
         let link = self.link.as_ref().map_or(String::from("unavailable"), |l| l.to_string());
 
         write!(f, "\nPublished: {} \n -- {} --\n{}\nRead more: {}\n", pub_date, self.title, self.description, link)
     }
 }
 
-fn fetch_articles(url: Url, articles: &mut Vec<Article>) {
-    let body = reqwest::blocking::get(url)
-        .expect("Could not establish connection")
-        .text()
-        .unwrap();
+/// Reads the `href` of an Atom `<link>` element, but only when it's the
+/// article's own alternate link. Atom entries commonly carry several
+/// `<link>`s (`rel="alternate"`, `rel="edit"`, `rel="self"`, ...); a
+/// missing `rel` defaults to `"alternate"` per the spec, so both count,
+/// while anything else (the feed's edit/self/replies URL) is ignored.
+fn alternate_link_href(e: &quick_xml::events::BytesStart) -> Option<String> {
+    let mut href = None;
+    let mut rel = None;
+
+    for attr in e.attributes().filter_map(|a| a.ok()) {
+        match attr.key.as_ref() {
+            b"href" => href = String::from_utf8(attr.value.to_vec()).ok(),
+            b"rel" => rel = String::from_utf8(attr.value.to_vec()).ok(),
+            _ => (),
+        }
+    }
+
+    match rel.as_deref() {
+        None | Some("alternate") => href,
+        _ => None,
+    }
+}
+
+async fn fetch_articles(client: &reqwest::Client, url: Url) -> Result<Vec<Article>, reqwest::Error> {
+    let body = client.get(url).send().await?.text().await?;
+
+    let fetched_at = Local::now().with_timezone(&local_offset());
 
     let mut reader = Reader::from_str(&body);
 
@@ -91,7 +170,9 @@ fn fetch_articles(url: Url, articles: &mut Vec<Article>) {
 
     let mut tag_stack: Vec<Tag> = Vec::new();
 
-    let mut current_item: Article = Article::new();
+    let mut current_item: Article = Article::new(fetched_at);
+
+    let mut articles: Vec<Article> = Vec::new();
 
     loop {
         match reader.read_event_into(&mut buf) {
@@ -99,11 +180,26 @@ fn fetch_articles(url: Url, articles: &mut Vec<Article>) {
                 let tag = Tag::from_str(str::from_utf8(e.name().as_ref()).unwrap()).ok().unwrap();
 
                 if tag == Item {
-                    current_item = Article::new();
+                    current_item = Article::new(fetched_at);
+                }
+
+                if tag == Link {
+                    if let Some(href) = alternate_link_href(&e) {
+                        current_item.update_field(&Link, &href);
+                    }
                 }
-                
+
                 tag_stack.push(tag);
             },
+            Ok(Event::Empty(e)) => {
+                let tag = Tag::from_str(str::from_utf8(e.name().as_ref()).unwrap()).ok().unwrap();
+
+                if tag == Link {
+                    if let Some(href) = alternate_link_href(&e) {
+                        current_item.update_field(&Link, &href);
+                    }
+                }
+            },
             Ok(Event::End(_)) => {
                 if let Some(tag) = tag_stack.pop() {
                     if tag == Item {
@@ -125,15 +221,13 @@ fn fetch_articles(url: Url, articles: &mut Vec<Article>) {
             },
             Ok(Event::Eof) => break,
             _ => ()
-        } 
+        }
     }
+
+    Ok(articles)
 }
 
 fn read_feeds(connection: &Connection) -> Vec<Url> {
-    connection
-        .execute("CREATE TABLE IF NOT EXISTS feeds (url TEXT PRIMARY KEY)")
-        .unwrap();
-
     connection
         .prepare("SELECT * FROM feeds")
         .unwrap()
@@ -142,19 +236,207 @@ fn read_feeds(connection: &Connection) -> Vec<Url> {
         .collect()
 }
 
-fn main() {
+fn article_guid(article: &Article) -> &str {
+    article.guid.as_deref()
+        .or_else(|| article.link.as_ref().map(|l| l.as_str()))
+        .unwrap_or(article.title.as_str())
+}
+
+fn store_articles(connection: &Connection, feed_url: &Url, articles: &[Article]) {
+    let mut statement = connection
+        .prepare(
+            "INSERT OR IGNORE INTO articles (feed_url, guid, title, description, pub_date, inferred, link, read)
+             VALUES (?, ?, ?, ?, ?, ?, ?, 0)",
+        )
+        .unwrap();
+
+    for article in articles {
+        statement.reset().unwrap();
+
+        statement.bind((1, feed_url.as_str())).unwrap();
+        statement.bind((2, article_guid(article))).unwrap();
+        statement.bind((3, article.title.as_str())).unwrap();
+        statement.bind((4, article.description.as_str())).unwrap();
+
+        match article.pub_date {
+            Some(date) => statement.bind((5, date.timestamp())).unwrap(),
+            None => statement.bind((5, sqlite::Value::Null)).unwrap(),
+        }
+
+        statement.bind((6, article.pub_date_inferred as i64)).unwrap();
+
+        match &article.link {
+            Some(link) => statement.bind((7, link.as_str())).unwrap(),
+            None => statement.bind((7, sqlite::Value::Null)).unwrap(),
+        }
+
+        statement.next().unwrap();
+    }
+}
+
+fn unread_count(connection: &Connection, feed_url: &Url) -> i64 {
+    let mut statement = connection
+        .prepare("SELECT COUNT(*) AS count FROM articles WHERE feed_url = ? AND read = 0")
+        .unwrap();
+
+    statement.bind((1, feed_url.as_str())).unwrap();
+    statement.next().unwrap();
+
+    statement.read::<i64, _>("count").unwrap()
+}
+
+fn article_from_row(row: &sqlite::Row) -> Article {
+    let mut article = Article::new(Local::now().with_timezone(&local_offset()));
+
+    article.title = row.read::<&str, _>("title").to_string();
+    article.description = row.read::<&str, _>("description").to_string();
+
+    if let Some(timestamp) = row.try_read::<i64, _>("pub_date").ok() {
+        let offset = local_offset();
+        article.pub_date = DateTime::from_timestamp(timestamp, 0).map(|d| d.with_timezone(&offset));
+    }
+
+    article.pub_date_inferred = row.read::<i64, _>("inferred") != 0;
+
+    if let Some(link) = row.try_read::<&str, _>("link").ok() {
+        article.link = Url::parse(link).ok();
+    }
+
+    article
+}
+
+fn print_unread_and_mark_read(connection: &Connection) {
+    let unread: Vec<Article> = connection
+        .prepare("SELECT * FROM articles WHERE read = 0 ORDER BY pub_date DESC")
+        .unwrap()
+        .into_iter()
+        .map(|row| article_from_row(&row.unwrap()))
+        .collect();
+
+    for article in &unread {
+        println!("{article}");
+    }
+
+    connection
+        .execute("UPDATE articles SET read = 1 WHERE read = 0")
+        .unwrap();
+}
+
+/// Filter criteria for querying stored articles, translated into a
+/// parameterized `WHERE` clause so user-supplied values are always bound
+/// rather than interpolated into the SQL string.
+struct ArticleFilter {
+    feed_url: Option<Url>,
+    since: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+    keyword: Option<String>,
+    limit: i64,
+}
+
+impl ArticleFilter {
+    fn new() -> Self {
+        ArticleFilter {
+            feed_url: None,
+            since: None,
+            until: None,
+            keyword: None,
+            limit: 50,
+        }
+    }
+
+    fn query(&self, connection: &Connection) -> Vec<Article> {
+        let mut sql = String::from("SELECT * FROM articles WHERE 1 = 1");
+        let mut params: Vec<sqlite::Value> = Vec::new();
+
+        if let Some(feed_url) = &self.feed_url {
+            sql.push_str(" AND feed_url = ?");
+            params.push(sqlite::Value::String(feed_url.to_string()));
+        }
+
+        if let Some(since) = self.since {
+            sql.push_str(" AND pub_date >= ?");
+            params.push(sqlite::Value::Integer(since.timestamp()));
+        }
+
+        if let Some(until) = self.until {
+            sql.push_str(" AND pub_date <= ?");
+            params.push(sqlite::Value::Integer(until.timestamp()));
+        }
+
+        if let Some(keyword) = &self.keyword {
+            sql.push_str(" AND (title LIKE ? OR description LIKE ?)");
+            let pattern = format!("%{keyword}%");
+            params.push(sqlite::Value::String(pattern.clone()));
+            params.push(sqlite::Value::String(pattern));
+        }
+
+        sql.push_str(" ORDER BY pub_date DESC LIMIT ?");
+        params.push(sqlite::Value::Integer(self.limit));
+
+        let mut statement = connection.prepare(sql).unwrap();
+
+        for (index, value) in params.into_iter().enumerate() {
+            statement.bind((index + 1, value)).unwrap();
+        }
+
+        statement
+            .into_iter()
+            .map(|row| article_from_row(&row.unwrap()))
+            .collect()
+    }
+}
+
+fn run_search(connection: &Connection, args: &[String]) {
+    let mut filter = ArticleFilter::new();
+    let mut i = 0;
+
+    while i < args.len() {
+        let value = args.get(i + 1).expect("Missing value for search flag");
+
+        match args[i].as_str() {
+            "--feed" => filter.feed_url = Some(Url::parse(value).expect("Invalid URL")),
+            "--since" => filter.since = Some(parse_date(value).expect("Invalid date")),
+            "--until" => filter.until = Some(parse_date(value).expect("Invalid date")),
+            "--keyword" => filter.keyword = Some(value.clone()),
+            "--limit" => filter.limit = value.parse().expect("Invalid limit"),
+            other => panic!("Unknown search flag: {other}"),
+        }
+
+        i += 2;
+    }
+
+    for article in filter.query(connection) {
+        println!("{article}");
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let connection = sqlite::open("reader.db").unwrap();
+    migrations::run(&connection);
+
+    let search_args: Vec<String> = env::args().skip(1).collect();
+
+    if search_args.first().map(String::as_str) == Some("search") {
+        run_search(&connection, &search_args[1..]);
+        return;
+    }
+
     let mut args = env::args();
     let parameter = args.nth(1);
     let value = args.next();
 
-    let connection = sqlite::open("reader.db").unwrap();
-
     match (parameter, value) {
         (Some(parameter), None) => {
             if parameter == "list" {
                 read_feeds(&connection)
                     .iter()
-                    .for_each(|feed| println!("{}", feed.as_str()));
+                    .for_each(|feed| println!("{} ({} unread)", feed.as_str(), unread_count(&connection, feed)));
+
+                return;
+            }
+            if parameter == "read" {
+                print_unread_and_mark_read(&connection);
 
                 return;
             }
@@ -165,10 +447,6 @@ fn main() {
             if parameter == String::from("add") {
                 Url::parse(&value).expect("Invalid URL");
 
-                connection
-                    .execute("CREATE TABLE IF NOT EXISTS feeds (url TEXT)")
-                    .unwrap();
-
                 let result = connection.execute(format!("INSERT INTO feeds VALUES ('{}')", value));
 
                 match result {
@@ -182,14 +460,10 @@ fn main() {
 
                 return;
             } else if parameter == String::from("remove") {
-                connection
-                    .execute("CREATE TABLE IF NOT EXISTS feeds (url TEXT)")
-                    .unwrap();
-
                 connection
                     .execute(format!("DELETE FROM feeds WHERE url = '{}'", value))
                     .unwrap();
-                
+
                 return;
             } else {
                 panic!("Not valid command");
@@ -198,13 +472,107 @@ fn main() {
     }
 
     let urls: Vec<Url> = read_feeds(&connection);
-    
-    let mut articles: Vec<Article> = Vec::new();
-    for url in urls {
-        fetch_articles(url, &mut articles);
+
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .unwrap();
+
+    let fetches = urls.into_iter().map(|url| {
+        let client = client.clone();
+        async move {
+            let result = fetch_articles(&client, url.clone()).await;
+            (url, result)
+        }
+    });
+
+    for (url, result) in join_all(fetches).await {
+        match result {
+            Ok(articles) => store_articles(&connection, &url, &articles),
+            Err(err) => eprintln!("Failed to fetch {}: {}", url, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quick_xml::events::BytesStart;
+
+    fn article(guid: Option<&str>, link: Option<&str>, title: &str) -> Article {
+        let mut article = Article::new(Local::now().with_timezone(&local_offset()));
+        article.guid = guid.map(String::from);
+        article.link = link.and_then(|l| Url::parse(l).ok());
+        article.title = title.to_string();
+        article
     }
 
-    for article in articles.iter().rev() {
-        println!("{article}");
+    #[test]
+    fn parse_date_accepts_rfc2822() {
+        assert!(parse_date("Tue, 01 Jul 2025 10:00:00 +0000").is_some());
+    }
+
+    #[test]
+    fn parse_date_accepts_rfc3339() {
+        assert!(parse_date("2025-07-01T10:00:00Z").is_some());
+    }
+
+    #[test]
+    fn parse_date_accepts_naive_fallback_formats() {
+        assert!(parse_date("2025-07-01 10:00:00").is_some());
+        assert!(parse_date("2025-07-01T10:00:00").is_some());
+        assert!(parse_date("2025-07-01").is_some());
+    }
+
+    #[test]
+    fn parse_date_rejects_unparseable_input() {
+        assert!(parse_date("not a date").is_none());
+    }
+
+    #[test]
+    fn article_guid_prefers_guid_over_link_and_title() {
+        let item = article(Some("tag:example.com,2024:1234"), Some("https://example.com/a"), "Title");
+        assert_eq!(article_guid(&item), "tag:example.com,2024:1234");
+    }
+
+    #[test]
+    fn article_guid_falls_back_to_link_when_no_guid() {
+        let item = article(None, Some("https://example.com/a"), "Title");
+        assert_eq!(article_guid(&item), "https://example.com/a");
+    }
+
+    #[test]
+    fn article_guid_falls_back_to_title_when_no_guid_or_link() {
+        let item = article(None, None, "Title");
+        assert_eq!(article_guid(&item), "Title");
+    }
+
+    fn link_tag(attrs: &[(&str, &str)]) -> BytesStart<'static> {
+        let mut tag = BytesStart::new("link");
+        for (key, value) in attrs {
+            tag.push_attribute((*key, *value));
+        }
+        tag
+    }
+
+    #[test]
+    fn alternate_link_href_accepts_missing_rel() {
+        let tag = link_tag(&[("href", "https://example.com/a")]);
+        assert_eq!(alternate_link_href(&tag), Some("https://example.com/a".to_string()));
+    }
+
+    #[test]
+    fn alternate_link_href_accepts_explicit_alternate_rel() {
+        let tag = link_tag(&[("href", "https://example.com/a"), ("rel", "alternate")]);
+        assert_eq!(alternate_link_href(&tag), Some("https://example.com/a".to_string()));
+    }
+
+    #[test]
+    fn alternate_link_href_rejects_edit_and_self_rel() {
+        let edit = link_tag(&[("href", "https://example.com/edit"), ("rel", "edit")]);
+        assert_eq!(alternate_link_href(&edit), None);
+
+        let self_link = link_tag(&[("href", "https://example.com/self"), ("rel", "self")]);
+        assert_eq!(alternate_link_href(&self_link), None);
     }
 }
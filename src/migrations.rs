@@ -0,0 +1,47 @@
+use sqlite::Connection;
+
+/// Ordered list of schema migrations. Each entry is applied exactly once,
+/// in order, and the applied count is tracked via `PRAGMA user_version`.
+/// Append new migrations to the end of this list; never edit or reorder
+/// existing ones once they have shipped.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS feeds (url TEXT PRIMARY KEY)",
+    "CREATE TABLE IF NOT EXISTS articles (
+        feed_url TEXT NOT NULL,
+        guid TEXT NOT NULL,
+        title TEXT NOT NULL,
+        description TEXT NOT NULL,
+        pub_date INTEGER,
+        read INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (feed_url, guid)
+    )",
+    "ALTER TABLE articles ADD COLUMN inferred INTEGER NOT NULL DEFAULT 0",
+    "ALTER TABLE articles ADD COLUMN link TEXT",
+];
+
+fn schema_version(connection: &Connection) -> i64 {
+    connection
+        .prepare("PRAGMA user_version")
+        .unwrap()
+        .into_iter()
+        .next()
+        .unwrap()
+        .unwrap()
+        .read::<i64, _>("user_version")
+}
+
+/// Applies every migration that has not yet run against `connection`,
+/// bumping `user_version` one step at a time so a crash mid-migration
+/// leaves the schema at a known, re-runnable version.
+pub fn run(connection: &Connection) {
+    let version = schema_version(connection) as usize;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        connection.execute("BEGIN").unwrap();
+        connection.execute(*migration).unwrap();
+        connection
+            .execute(format!("PRAGMA user_version = {}", index + 1))
+            .unwrap();
+        connection.execute("COMMIT").unwrap();
+    }
+}